@@ -0,0 +1,410 @@
+// Authentication and role-based access control. Identity is carried in a
+// signed JWT (see OneAuth's token-carried identity pattern): the login
+// handler issues it, and the `AuthUser` extractor validates and decodes it
+// on every subsequent request so handlers can gate on `user.role`.
+
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest, HttpResponse};
+use futures_util::future::{ready, Ready};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqlitePool};
+use std::env;
+
+use crate::error::Error as ApiError;
+use crate::{crypto, ApiResponse};
+use crate::db::Db;
+
+const TOKEN_TTL_HOURS: i64 = 24;
+const RESET_TOKEN_TTL_HOURS: i64 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Role {
+    Admin,
+    Doctor,
+    Nurse,
+    Receptionist,
+}
+
+impl std::str::FromStr for Role {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "admin" => Ok(Role::Admin),
+            "doctor" => Ok(Role::Doctor),
+            "nurse" => Ok(Role::Nurse),
+            "receptionist" => Ok(Role::Receptionist),
+            _ => Err(()),
+        }
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Role::Admin => "admin",
+            Role::Doctor => "doctor",
+            Role::Nurse => "nurse",
+            Role::Receptionist => "receptionist",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct UserRecord {
+    id: i64,
+    username: String,
+    password_hash: String,
+    role: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RegisterRequest {
+    username: String,
+    password: String,
+    role: String,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub(crate) struct User {
+    id: i64,
+    username: String,
+    role: String,
+    created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct LoginRequest {
+    username: String,
+    password: String,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct LoginResponse {
+    token: String,
+    role: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ForgotPasswordRequest {
+    username: String,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ForgotPasswordResponse {
+    reset_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ResetPasswordRequest {
+    token: String,
+    new_password: String,
+}
+
+#[derive(Debug, FromRow)]
+struct PasswordResetTokenRecord {
+    user_id: i64,
+    expires_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: i64,
+    username: String,
+    role: String,
+    exp: i64,
+}
+
+fn jwt_secret() -> String {
+    env::var("JWT_SECRET").unwrap_or_else(|_| "dev-secret-change-me".to_string())
+}
+
+// Identity injected into handlers by the `AuthUser` extractor once a bearer
+// token has been validated.
+#[derive(Debug, Clone)]
+pub(crate) struct AuthUser {
+    pub(crate) id: i64,
+    #[allow(dead_code)]
+    pub(crate) username: String,
+    pub(crate) role: Role,
+}
+
+impl FromRequest for AuthUser {
+    type Error = ApiError;
+    type Future = Ready<std::result::Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let token = req
+            .headers()
+            .get("Authorization")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "));
+
+        let token = match token {
+            Some(t) => t,
+            None => return ready(Err(ApiError::Unauthorized("Missing bearer token".to_string()))),
+        };
+
+        let decoded = decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(jwt_secret().as_bytes()),
+            &Validation::default(),
+        );
+
+        let claims = match decoded {
+            Ok(data) => data.claims,
+            Err(_) => return ready(Err(ApiError::Unauthorized("Invalid or expired token".to_string()))),
+        };
+
+        let role = match claims.role.parse::<Role>() {
+            Ok(r) => r,
+            Err(_) => return ready(Err(ApiError::Unauthorized("Unknown role in token".to_string()))),
+        };
+
+        ready(Ok(AuthUser {
+            id: claims.sub,
+            username: claims.username,
+            role,
+        }))
+    }
+}
+
+// Returns a 403 response when `user`'s role isn't one of `allowed`, or
+// `None` when the request may proceed.
+pub(crate) fn require_roles(user: &AuthUser, allowed: &[Role]) -> Option<HttpResponse> {
+    if allowed.contains(&user.role) {
+        None
+    } else {
+        Some(HttpResponse::Forbidden().json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: Some("You do not have permission to perform this action".to_string()),
+        }))
+    }
+}
+
+pub(crate) async fn register(
+    user: Option<AuthUser>,
+    pool: web::Data<SqlitePool>,
+    new_user: web::Json<RegisterRequest>,
+) -> std::result::Result<HttpResponse, ApiError> {
+    let existing_users: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+        .fetch_one(pool.get_ref())
+        .await?;
+
+    // The very first account bootstraps the system unauthenticated (there's
+    // no admin yet to create it); every account after that needs one.
+    if existing_users > 0 {
+        match user {
+            Some(u) => {
+                if let Some(resp) = require_roles(&u, &[Role::Admin]) {
+                    return Ok(resp);
+                }
+            }
+            None => return Err(ApiError::Unauthorized("Missing bearer token".to_string())),
+        }
+    }
+
+    if new_user.role.parse::<Role>().is_err() {
+        return Err(ApiError::Validation(format!("Unknown role: {}", new_user.role)));
+    }
+
+    let password = new_user.password.clone();
+    let password_hash = web::block(move || crypto::hash(&password))
+        .await
+        .map_err(|_| ApiError::Internal("password hashing task failed".to_string()))?;
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut db = Db::begin_immediate(pool.get_ref()).await?;
+
+    // Re-check under the write lock: the count above was read outside any
+    // transaction, so two unauthenticated bootstrap requests can both have
+    // seen an empty table. Only one of them should get to self-assign admin.
+    if existing_users == 0 {
+        let recheck: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(db.executor())
+            .await?;
+        if recheck > 0 {
+            return Err(ApiError::Unauthorized("Missing bearer token".to_string()));
+        }
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO users (username, password_hash, role, created_at) VALUES (?, ?, ?, ?)",
+    )
+    .bind(&new_user.username)
+    .bind(&password_hash)
+    .bind(&new_user.role)
+    .bind(&now)
+    .execute(db.executor())
+    .await?;
+
+    let created = sqlx::query_as::<_, User>("SELECT id, username, role, created_at FROM users WHERE id = ?")
+        .bind(result.last_insert_rowid())
+        .fetch_one(db.executor())
+        .await?;
+
+    db.commit().await?;
+
+    Ok(HttpResponse::Created().json(ApiResponse {
+        success: true,
+        data: Some(created),
+        message: Some("User created successfully".to_string()),
+    }))
+}
+
+pub(crate) async fn login(
+    pool: web::Data<SqlitePool>,
+    credentials: web::Json<LoginRequest>,
+) -> std::result::Result<HttpResponse, ApiError> {
+    let user = sqlx::query_as::<_, UserRecord>("SELECT * FROM users WHERE username = ?")
+        .bind(&credentials.username)
+        .fetch_optional(pool.get_ref())
+        .await?;
+
+    let password = credentials.password.clone();
+    let user = match user {
+        Some(u) => {
+            let hash = u.password_hash.clone();
+            let valid = web::block(move || crypto::verify(&password, &hash))
+                .await
+                .map_err(|_| ApiError::Internal("password verification task failed".to_string()))?;
+            if valid {
+                u
+            } else {
+                return Ok(HttpResponse::Unauthorized().json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    message: Some("Invalid username or password".to_string()),
+                }));
+            }
+        }
+        None => {
+            return Ok(HttpResponse::Unauthorized().json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: Some("Invalid username or password".to_string()),
+            }))
+        }
+    };
+
+    let exp = (chrono::Utc::now() + chrono::Duration::hours(TOKEN_TTL_HOURS)).timestamp();
+    let claims = Claims {
+        sub: user.id,
+        username: user.username,
+        role: user.role.clone(),
+        exp,
+    };
+
+    let token = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(jwt_secret().as_bytes()),
+    )
+    .expect("JWT encoding should not fail for well-formed claims");
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(LoginResponse {
+            token,
+            role: user.role,
+        }),
+        message: None,
+    }))
+}
+
+pub(crate) async fn forgot_password(
+    pool: web::Data<SqlitePool>,
+    request: web::Json<ForgotPasswordRequest>,
+) -> std::result::Result<HttpResponse, ApiError> {
+    let user = sqlx::query_as::<_, UserRecord>("SELECT * FROM users WHERE username = ?")
+        .bind(&request.username)
+        .fetch_optional(pool.get_ref())
+        .await?;
+
+    let user = match user {
+        Some(u) => u,
+        None => {
+            return Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+                success: true,
+                data: None,
+                message: Some("If that account exists, a reset token has been issued".to_string()),
+            }))
+        }
+    };
+
+    let token = crypto::random();
+    let expires_at = (chrono::Utc::now() + chrono::Duration::hours(RESET_TOKEN_TTL_HOURS)).to_rfc3339();
+
+    sqlx::query(
+        "INSERT INTO password_reset_tokens (token, user_id, expires_at) VALUES (?, ?, ?)",
+    )
+    .bind(&token)
+    .bind(user.id)
+    .bind(&expires_at)
+    .execute(pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        data: Some(ForgotPasswordResponse { reset_token: token }),
+        message: None,
+    }))
+}
+
+pub(crate) async fn reset_password(
+    pool: web::Data<SqlitePool>,
+    request: web::Json<ResetPasswordRequest>,
+) -> std::result::Result<HttpResponse, ApiError> {
+    let mut db = Db::begin(pool.get_ref()).await?;
+
+    let reset = sqlx::query_as::<_, PasswordResetTokenRecord>(
+        "SELECT user_id, expires_at FROM password_reset_tokens WHERE token = ?",
+    )
+    .bind(&request.token)
+    .fetch_optional(db.executor())
+    .await?;
+
+    let reset = match reset {
+        Some(r) => r,
+        None => return Err(ApiError::Validation("Invalid or expired reset token".to_string())),
+    };
+
+    let expires_at: chrono::DateTime<chrono::Utc> = reset
+        .expires_at
+        .parse()
+        .map_err(|_| ApiError::Database(sqlx::Error::Decode("invalid expires_at timestamp".into())))?;
+    if expires_at < chrono::Utc::now() {
+        sqlx::query("DELETE FROM password_reset_tokens WHERE token = ?")
+            .bind(&request.token)
+            .execute(db.executor())
+            .await?;
+        db.commit().await?;
+
+        return Err(ApiError::Validation("Invalid or expired reset token".to_string()));
+    }
+
+    let new_password = request.new_password.clone();
+    let password_hash = web::block(move || crypto::hash(&new_password))
+        .await
+        .map_err(|_| ApiError::Internal("password hashing task failed".to_string()))?;
+    sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
+        .bind(&password_hash)
+        .bind(reset.user_id)
+        .execute(db.executor())
+        .await?;
+
+    sqlx::query("DELETE FROM password_reset_tokens WHERE token = ?")
+        .bind(&request.token)
+        .execute(db.executor())
+        .await?;
+
+    db.commit().await?;
+
+    Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+        success: true,
+        data: None,
+        message: Some("Password reset successfully".to_string()),
+    }))
+}
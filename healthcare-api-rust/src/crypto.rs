@@ -0,0 +1,55 @@
+// Password hashing and high-entropy token generation, used for user
+// credentials and password-reset tokens.
+
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+const RANDOM_TOKEN_LEN: usize = 32;
+
+pub(crate) fn hash(plaintext: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(plaintext.as_bytes(), &salt)
+        .expect("argon2 hashing should not fail for a valid password")
+        .to_string()
+}
+
+pub(crate) fn verify(plaintext: &str, hash: &str) -> bool {
+    match PasswordHash::new(hash) {
+        Ok(parsed) => Argon2::default()
+            .verify_password(plaintext.as_bytes(), &parsed)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+pub(crate) fn random() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(RANDOM_TOKEN_LEN)
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn hash_then_verify_round_trips() {
+        let hashed = hash("correct horse battery staple");
+        assert!(verify("correct horse battery staple", &hashed));
+        assert!(!verify("wrong password", &hashed));
+    }
+
+    #[test]
+    fn random_tokens_are_unique_and_sufficiently_long() {
+        let tokens: HashSet<String> = (0..10_000).map(|_| random()).collect();
+        assert_eq!(tokens.len(), 10_000);
+        assert!(tokens.iter().all(|t| t.len() >= 20));
+    }
+}
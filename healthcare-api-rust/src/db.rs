@@ -0,0 +1,42 @@
+// Per-request transaction wrapper. Mutating handlers begin a `Db` at the
+// top of the request, run their INSERT/UPDATE and the follow-up SELECT
+// against it, and commit only once every step has succeeded — a failure
+// anywhere rolls the whole request back instead of leaving a half-applied
+// write behind.
+
+use sqlx::{Sqlite, SqlitePool, Transaction};
+
+use crate::error::Error;
+
+pub(crate) struct Db {
+    tx: Transaction<'static, Sqlite>,
+}
+
+impl Db {
+    pub(crate) async fn begin(pool: &SqlitePool) -> Result<Self, Error> {
+        Ok(Db {
+            tx: pool.begin().await?,
+        })
+    }
+
+    // A plain `BEGIN` is deferred: it doesn't take SQLite's write lock until
+    // the first write statement runs, so a read-then-write check (has this
+    // already been created? is this slot still free?) can race with another
+    // connection's identical check before either has written anything. Use
+    // this instead of `begin` whenever a handler re-validates an invariant
+    // inside the transaction right before writing.
+    pub(crate) async fn begin_immediate(pool: &SqlitePool) -> Result<Self, Error> {
+        Ok(Db {
+            tx: Transaction::begin_with(pool, "BEGIN IMMEDIATE").await?,
+        })
+    }
+
+    pub(crate) fn executor(&mut self) -> &mut Transaction<'static, Sqlite> {
+        &mut self.tx
+    }
+
+    pub(crate) async fn commit(self) -> Result<(), Error> {
+        self.tx.commit().await?;
+        Ok(())
+    }
+}
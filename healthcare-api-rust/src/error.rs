@@ -0,0 +1,65 @@
+// Crate-wide error type. Replaces the `.unwrap()`s that used to panic the
+// worker on any DB hiccup: handlers now return `Result<HttpResponse, Error>`
+// and propagate failures with `?`, and `ResponseError` maps each variant to
+// the right HTTP status.
+
+use actix_web::{HttpResponse, ResponseError};
+use std::fmt;
+
+use crate::ApiResponse;
+
+#[derive(Debug)]
+pub(crate) enum Error {
+    Database(sqlx::Error),
+    NotFound(String),
+    Validation(String),
+    Conflict(String),
+    Unauthorized(String),
+    Internal(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Database(e) => write!(f, "Database error: {}", e),
+            Error::NotFound(msg) => write!(f, "{}", msg),
+            Error::Validation(msg) => write!(f, "{}", msg),
+            Error::Conflict(msg) => write!(f, "{}", msg),
+            Error::Unauthorized(msg) => write!(f, "{}", msg),
+            Error::Internal(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<sqlx::Error> for Error {
+    fn from(e: sqlx::Error) -> Self {
+        match &e {
+            sqlx::Error::RowNotFound => Error::NotFound("Resource not found".to_string()),
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                Error::Validation(format!("Duplicate value: {}", db_err.message()))
+            }
+            _ => Error::Database(e),
+        }
+    }
+}
+
+impl ResponseError for Error {
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        match self {
+            Error::Database(_) => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+            Error::NotFound(_) => actix_web::http::StatusCode::NOT_FOUND,
+            Error::Validation(_) => actix_web::http::StatusCode::BAD_REQUEST,
+            Error::Conflict(_) => actix_web::http::StatusCode::CONFLICT,
+            Error::Unauthorized(_) => actix_web::http::StatusCode::UNAUTHORIZED,
+            Error::Internal(_) => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            message: Some(self.to_string()),
+        })
+    }
+}
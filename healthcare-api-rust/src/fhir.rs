@@ -0,0 +1,759 @@
+// FHIR R4 interop layer: converts our native resources to/from FHIR JSON
+// and exposes a parallel set of routes under /fhir for EHR systems that
+// only speak FHIR. The native single-resource GET handlers
+// (`/api/{patients,appointments,prescriptions}/{id}`) also return a FHIR
+// body when the caller sends `Accept: application/fhir+json`; the native
+// list, create and update handlers always answer with the native JSON.
+
+use crate::{
+    Appointment, CreateAppointment, CreatePatient, CreatePrescription, Patient, Prescription,
+};
+use crate::auth;
+use crate::check_appointment_conflict;
+use crate::db::Db;
+use crate::error::Error;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+pub(crate) const FHIR_JSON: &str = "application/fhir+json";
+
+pub(crate) fn wants_fhir(req: &HttpRequest) -> bool {
+    req.headers()
+        .get("Accept")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.contains(FHIR_JSON))
+        .unwrap_or(false)
+}
+
+#[derive(Debug, Serialize)]
+struct FhirOperationOutcome {
+    #[serde(rename = "resourceType")]
+    resource_type: &'static str,
+    issue: Vec<FhirIssue>,
+}
+
+#[derive(Debug, Serialize)]
+struct FhirIssue {
+    severity: &'static str,
+    code: &'static str,
+    diagnostics: String,
+}
+
+fn operation_outcome(diagnostics: impl Into<String>) -> FhirOperationOutcome {
+    FhirOperationOutcome {
+        resource_type: "OperationOutcome",
+        issue: vec![FhirIssue {
+            severity: "error",
+            code: "invalid",
+            diagnostics: diagnostics.into(),
+        }],
+    }
+}
+
+// Search-result wrapper for the `GET /fhir/{ResourceType}` list routes, per
+// the FHIR Bundle resource shape.
+#[derive(Debug, Serialize)]
+struct FhirBundleEntry<T> {
+    resource: T,
+}
+
+#[derive(Debug, Serialize)]
+struct FhirBundle<T> {
+    #[serde(rename = "resourceType")]
+    resource_type: &'static str,
+    #[serde(rename = "type")]
+    bundle_type: &'static str,
+    total: usize,
+    entry: Vec<FhirBundleEntry<T>>,
+}
+
+fn searchset_bundle<T>(resources: Vec<T>) -> FhirBundle<T> {
+    FhirBundle {
+        resource_type: "Bundle",
+        bundle_type: "searchset",
+        total: resources.len(),
+        entry: resources
+            .into_iter()
+            .map(|resource| FhirBundleEntry { resource })
+            .collect(),
+    }
+}
+
+// --- Patient --------------------------------------------------------------
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FhirHumanName {
+    family: String,
+    given: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FhirContactPoint {
+    system: String,
+    value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FhirAddress {
+    text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FhirExtension {
+    url: String,
+    #[serde(rename = "valueString")]
+    value_string: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct FhirPatient {
+    #[serde(rename = "resourceType")]
+    resource_type: String,
+    id: Option<String>,
+    name: Vec<FhirHumanName>,
+    telecom: Vec<FhirContactPoint>,
+    #[serde(rename = "birthDate")]
+    birth_date: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<Vec<FhirAddress>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    extension: Vec<FhirExtension>,
+}
+
+const EXT_MEDICAL_HISTORY: &str = "https://healthcare-api.local/fhir/StructureDefinition/medical-history";
+const EXT_BLOOD_TYPE: &str = "https://healthcare-api.local/fhir/StructureDefinition/blood-type";
+
+impl From<&Patient> for FhirPatient {
+    fn from(p: &Patient) -> Self {
+        let mut extension = Vec::new();
+        if let Some(history) = &p.medical_history {
+            extension.push(FhirExtension {
+                url: EXT_MEDICAL_HISTORY.to_string(),
+                value_string: history.clone(),
+            });
+        }
+        if let Some(blood_type) = &p.blood_type {
+            extension.push(FhirExtension {
+                url: EXT_BLOOD_TYPE.to_string(),
+                value_string: blood_type.clone(),
+            });
+        }
+
+        FhirPatient {
+            resource_type: "Patient".to_string(),
+            id: Some(p.id.to_string()),
+            name: vec![FhirHumanName {
+                family: p.last_name.clone(),
+                given: vec![p.first_name.clone()],
+            }],
+            telecom: vec![
+                FhirContactPoint {
+                    system: "phone".to_string(),
+                    value: p.phone.clone(),
+                },
+                FhirContactPoint {
+                    system: "email".to_string(),
+                    value: p.email.clone(),
+                },
+            ],
+            birth_date: p.date_of_birth.clone(),
+            address: p.address.clone().map(|text| vec![FhirAddress { text }]),
+            extension,
+        }
+    }
+}
+
+impl FhirPatient {
+    fn into_create_patient(self) -> std::result::Result<CreatePatient, String> {
+        let name = self
+            .name
+            .into_iter()
+            .next()
+            .ok_or_else(|| "Patient.name is required".to_string())?;
+        let first_name = name
+            .given
+            .into_iter()
+            .next()
+            .ok_or_else(|| "Patient.name.given is required".to_string())?;
+
+        let phone = self
+            .telecom
+            .iter()
+            .find(|t| t.system == "phone")
+            .map(|t| t.value.clone())
+            .ok_or_else(|| "Patient.telecom with system=phone is required".to_string())?;
+        let email = self
+            .telecom
+            .iter()
+            .find(|t| t.system == "email")
+            .map(|t| t.value.clone())
+            .ok_or_else(|| "Patient.telecom with system=email is required".to_string())?;
+
+        let medical_history = self
+            .extension
+            .iter()
+            .find(|e| e.url == EXT_MEDICAL_HISTORY)
+            .map(|e| e.value_string.clone());
+        let blood_type = self
+            .extension
+            .iter()
+            .find(|e| e.url == EXT_BLOOD_TYPE)
+            .map(|e| e.value_string.clone());
+
+        Ok(CreatePatient {
+            first_name,
+            last_name: name.family,
+            email,
+            phone,
+            date_of_birth: self.birth_date,
+            address: self.address.and_then(|a| a.into_iter().next()).map(|a| a.text),
+            medical_history,
+            blood_type,
+        })
+    }
+}
+
+pub(crate) async fn list_patients_fhir(
+    _user: auth::AuthUser,
+    pool: web::Data<SqlitePool>,
+) -> Result<HttpResponse, Error> {
+    let patients = sqlx::query_as::<_, Patient>("SELECT * FROM patients ORDER BY created_at DESC")
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(FHIR_JSON)
+        .json(searchset_bundle(
+            patients.iter().map(FhirPatient::from).collect(),
+        )))
+}
+
+pub(crate) async fn get_patient_fhir(
+    _user: auth::AuthUser,
+    pool: web::Data<SqlitePool>,
+    patient_id: web::Path<i64>,
+) -> Result<HttpResponse, Error> {
+    let patient = sqlx::query_as::<_, Patient>("SELECT * FROM patients WHERE id = ?")
+        .bind(patient_id.into_inner())
+        .fetch_optional(pool.get_ref())
+        .await?;
+
+    match patient {
+        Some(p) => Ok(HttpResponse::Ok()
+            .content_type(FHIR_JSON)
+            .json(FhirPatient::from(&p))),
+        None => Ok(HttpResponse::NotFound()
+            .content_type(FHIR_JSON)
+            .json(operation_outcome("Patient not found"))),
+    }
+}
+
+pub(crate) async fn create_patient_fhir(
+    user: auth::AuthUser,
+    pool: web::Data<SqlitePool>,
+    resource: web::Json<FhirPatient>,
+) -> Result<HttpResponse, Error> {
+    if let Some(resp) = auth::require_roles(
+        &user,
+        &[auth::Role::Admin, auth::Role::Doctor, auth::Role::Nurse, auth::Role::Receptionist],
+    ) {
+        return Ok(resp);
+    }
+
+    let create = match resource.into_inner().into_create_patient() {
+        Ok(c) => c,
+        Err(e) => {
+            return Ok(HttpResponse::BadRequest()
+                .content_type(FHIR_JSON)
+                .json(operation_outcome(e)))
+        }
+    };
+
+    let now = Utc::now().to_rfc3339();
+    let mut db = Db::begin(pool.get_ref()).await?;
+
+    let result = sqlx::query(
+        "INSERT INTO patients (first_name, last_name, email, phone, date_of_birth, address, medical_history, blood_type, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&create.first_name)
+    .bind(&create.last_name)
+    .bind(&create.email)
+    .bind(&create.phone)
+    .bind(&create.date_of_birth)
+    .bind(&create.address)
+    .bind(&create.medical_history)
+    .bind(&create.blood_type)
+    .bind(&now)
+    .bind(&now)
+    .execute(db.executor())
+    .await?;
+
+    let created = sqlx::query_as::<_, Patient>("SELECT * FROM patients WHERE id = ?")
+        .bind(result.last_insert_rowid())
+        .fetch_one(db.executor())
+        .await?;
+
+    db.commit().await?;
+
+    Ok(HttpResponse::Created()
+        .content_type(FHIR_JSON)
+        .json(FhirPatient::from(&created)))
+}
+
+// --- Appointment ------------------------------------------------------------
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FhirReference {
+    reference: String,
+    display: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FhirParticipant {
+    actor: FhirReference,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct FhirAppointment {
+    #[serde(rename = "resourceType")]
+    resource_type: String,
+    id: Option<String>,
+    status: String,
+    description: String,
+    start: String,
+    end: String,
+    participant: Vec<FhirParticipant>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+fn appointment_status_to_fhir(status: &str) -> &'static str {
+    match status {
+        "scheduled" => "booked",
+        "completed" => "fulfilled",
+        "cancelled" => "cancelled",
+        _ => "pending",
+    }
+}
+
+impl From<&Appointment> for FhirAppointment {
+    fn from(a: &Appointment) -> Self {
+        let start: DateTime<Utc> = a
+            .appointment_date
+            .parse()
+            .unwrap_or_else(|_| Utc::now());
+        let end = start + Duration::minutes(a.duration_minutes as i64);
+
+        FhirAppointment {
+            resource_type: "Appointment".to_string(),
+            id: Some(a.id.to_string()),
+            status: appointment_status_to_fhir(&a.status).to_string(),
+            description: a.reason.clone(),
+            start: start.to_rfc3339(),
+            end: end.to_rfc3339(),
+            participant: vec![
+                FhirParticipant {
+                    actor: FhirReference {
+                        reference: format!("Patient/{}", a.patient_id),
+                        display: None,
+                    },
+                },
+                FhirParticipant {
+                    actor: FhirReference {
+                        reference: "Practitioner".to_string(),
+                        display: Some(a.doctor_name.clone()),
+                    },
+                },
+            ],
+            comment: a.notes.clone(),
+        }
+    }
+}
+
+impl FhirAppointment {
+    fn into_create_appointment(self) -> std::result::Result<CreateAppointment, String> {
+        let patient_ref = self
+            .participant
+            .iter()
+            .map(|p| &p.actor)
+            .find(|a| a.reference.starts_with("Patient/"))
+            .ok_or_else(|| "Appointment.participant with a Patient/{id} actor is required".to_string())?;
+        let patient_id = patient_ref
+            .reference
+            .trim_start_matches("Patient/")
+            .parse::<i64>()
+            .map_err(|_| "Appointment.participant Patient reference is not a valid id".to_string())?;
+
+        let doctor_name = self
+            .participant
+            .iter()
+            .map(|p| &p.actor)
+            .find(|a| a.reference == "Practitioner")
+            .and_then(|a| a.display.clone())
+            .ok_or_else(|| "Appointment.participant with a Practitioner actor is required".to_string())?;
+
+        let start: DateTime<Utc> = self
+            .start
+            .parse()
+            .map_err(|_| "Appointment.start must be RFC3339".to_string())?;
+        let end: DateTime<Utc> = self
+            .end
+            .parse()
+            .map_err(|_| "Appointment.end must be RFC3339".to_string())?;
+        let duration_minutes = (end - start).num_minutes() as i32;
+
+        Ok(CreateAppointment {
+            patient_id,
+            doctor_name,
+            appointment_date: self.start,
+            duration_minutes,
+            reason: self.description,
+            notes: self.comment,
+        })
+    }
+}
+
+pub(crate) async fn list_appointments_fhir(
+    _user: auth::AuthUser,
+    pool: web::Data<SqlitePool>,
+) -> Result<HttpResponse, Error> {
+    let appointments = sqlx::query_as::<_, Appointment>(
+        "SELECT * FROM appointments ORDER BY appointment_date DESC"
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(FHIR_JSON)
+        .json(searchset_bundle(
+            appointments.iter().map(FhirAppointment::from).collect(),
+        )))
+}
+
+pub(crate) async fn get_appointment_fhir(
+    _user: auth::AuthUser,
+    pool: web::Data<SqlitePool>,
+    appointment_id: web::Path<i64>,
+) -> Result<HttpResponse, Error> {
+    let appointment = sqlx::query_as::<_, Appointment>("SELECT * FROM appointments WHERE id = ?")
+        .bind(appointment_id.into_inner())
+        .fetch_optional(pool.get_ref())
+        .await?;
+
+    match appointment {
+        Some(a) => Ok(HttpResponse::Ok()
+            .content_type(FHIR_JSON)
+            .json(FhirAppointment::from(&a))),
+        None => Ok(HttpResponse::NotFound()
+            .content_type(FHIR_JSON)
+            .json(operation_outcome("Appointment not found"))),
+    }
+}
+
+pub(crate) async fn create_appointment_fhir(
+    user: auth::AuthUser,
+    pool: web::Data<SqlitePool>,
+    resource: web::Json<FhirAppointment>,
+) -> Result<HttpResponse, Error> {
+    if let Some(resp) = auth::require_roles(
+        &user,
+        &[auth::Role::Admin, auth::Role::Doctor, auth::Role::Nurse, auth::Role::Receptionist],
+    ) {
+        return Ok(resp);
+    }
+
+    let create = match resource.into_inner().into_create_appointment() {
+        Ok(c) => c,
+        Err(e) => {
+            return Ok(HttpResponse::BadRequest()
+                .content_type(FHIR_JSON)
+                .json(operation_outcome(e)))
+        }
+    };
+
+    let now = Utc::now().to_rfc3339();
+    // BEGIN IMMEDIATE, same as the native /api/appointments path: the
+    // conflict check only SELECTs, so a plain deferred transaction wouldn't
+    // take the write lock until the INSERT below, letting two overlapping
+    // imports both pass the check before either commits.
+    let mut db = Db::begin_immediate(pool.get_ref()).await?;
+
+    check_appointment_conflict(
+        &mut db,
+        &create.doctor_name,
+        &create.appointment_date,
+        create.duration_minutes,
+        None,
+    )
+    .await?;
+
+    let result = sqlx::query(
+        "INSERT INTO appointments (patient_id, doctor_name, appointment_date, duration_minutes, status, reason, notes, created_at, updated_at)
+         VALUES (?, ?, ?, ?, 'scheduled', ?, ?, ?, ?)"
+    )
+    .bind(create.patient_id)
+    .bind(&create.doctor_name)
+    .bind(&create.appointment_date)
+    .bind(create.duration_minutes)
+    .bind(&create.reason)
+    .bind(&create.notes)
+    .bind(&now)
+    .bind(&now)
+    .execute(db.executor())
+    .await?;
+
+    let created = sqlx::query_as::<_, Appointment>("SELECT * FROM appointments WHERE id = ?")
+        .bind(result.last_insert_rowid())
+        .fetch_one(db.executor())
+        .await?;
+
+    db.commit().await?;
+
+    Ok(HttpResponse::Created()
+        .content_type(FHIR_JSON)
+        .json(FhirAppointment::from(&created)))
+}
+
+// --- Prescription / MedicationRequest ---------------------------------------
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FhirCodeableConcept {
+    text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FhirTiming {
+    code: FhirCodeableConcept,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FhirDosage {
+    text: String,
+    timing: FhirTiming,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FhirPeriod {
+    start: String,
+    end: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FhirDispenseRequest {
+    #[serde(rename = "validityPeriod")]
+    validity_period: FhirPeriod,
+    #[serde(rename = "numberOfRepeatsAllowed")]
+    number_of_repeats_allowed: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FhirAnnotation {
+    text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct FhirMedicationRequest {
+    #[serde(rename = "resourceType")]
+    resource_type: String,
+    id: Option<String>,
+    status: String,
+    intent: String,
+    #[serde(rename = "medicationCodeableConcept")]
+    medication_codeable_concept: FhirCodeableConcept,
+    subject: FhirReference,
+    requester: FhirReference,
+    #[serde(rename = "dosageInstruction")]
+    dosage_instruction: Vec<FhirDosage>,
+    #[serde(rename = "authoredOn")]
+    authored_on: String,
+    #[serde(rename = "dispenseRequest")]
+    dispense_request: FhirDispenseRequest,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    note: Vec<FhirAnnotation>,
+}
+
+impl From<&Prescription> for FhirMedicationRequest {
+    fn from(p: &Prescription) -> Self {
+        FhirMedicationRequest {
+            resource_type: "MedicationRequest".to_string(),
+            id: Some(p.id.to_string()),
+            status: if p.refills_remaining > 0 { "active" } else { "completed" }.to_string(),
+            intent: "order".to_string(),
+            medication_codeable_concept: FhirCodeableConcept {
+                text: p.medication_name.clone(),
+            },
+            subject: FhirReference {
+                reference: format!("Patient/{}", p.patient_id),
+                display: None,
+            },
+            requester: FhirReference {
+                reference: "Practitioner".to_string(),
+                display: Some(p.prescribing_doctor.clone()),
+            },
+            dosage_instruction: vec![FhirDosage {
+                text: p.dosage.clone(),
+                timing: FhirTiming {
+                    code: FhirCodeableConcept {
+                        text: p.frequency.clone(),
+                    },
+                },
+            }],
+            authored_on: p.issued_date.clone(),
+            dispense_request: FhirDispenseRequest {
+                validity_period: FhirPeriod {
+                    start: p.issued_date.clone(),
+                    end: p.expiry_date.clone(),
+                },
+                number_of_repeats_allowed: p.refills_remaining,
+            },
+            note: p
+                .instructions
+                .clone()
+                .map(|text| vec![FhirAnnotation { text }])
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl FhirMedicationRequest {
+    fn into_create_prescription(self) -> std::result::Result<CreatePrescription, String> {
+        let patient_id = self
+            .subject
+            .reference
+            .trim_start_matches("Patient/")
+            .parse::<i64>()
+            .map_err(|_| "MedicationRequest.subject reference is not a valid Patient id".to_string())?;
+
+        let prescribing_doctor = self
+            .requester
+            .display
+            .ok_or_else(|| "MedicationRequest.requester.display is required".to_string())?;
+
+        let dosage_instruction = self
+            .dosage_instruction
+            .into_iter()
+            .next()
+            .ok_or_else(|| "MedicationRequest.dosageInstruction is required".to_string())?;
+        let dosage = dosage_instruction.text;
+        let frequency = dosage_instruction.timing.code.text;
+
+        let duration_days = (self
+            .dispense_request
+            .validity_period
+            .end
+            .parse::<DateTime<Utc>>()
+            .map_err(|_| "dispenseRequest.validityPeriod.end must be RFC3339".to_string())?
+            - self
+                .dispense_request
+                .validity_period
+                .start
+                .parse::<DateTime<Utc>>()
+                .map_err(|_| "dispenseRequest.validityPeriod.start must be RFC3339".to_string())?)
+        .num_days() as i32;
+
+        Ok(CreatePrescription {
+            patient_id,
+            medication_name: self.medication_codeable_concept.text,
+            dosage,
+            frequency,
+            duration_days,
+            prescribing_doctor,
+            instructions: self.note.into_iter().next().map(|n| n.text),
+            refills_remaining: self.dispense_request.number_of_repeats_allowed,
+        })
+    }
+}
+
+pub(crate) async fn list_prescriptions_fhir(
+    _user: auth::AuthUser,
+    pool: web::Data<SqlitePool>,
+) -> Result<HttpResponse, Error> {
+    let prescriptions = sqlx::query_as::<_, Prescription>(
+        "SELECT * FROM prescriptions ORDER BY issued_date DESC"
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(FHIR_JSON)
+        .json(searchset_bundle(
+            prescriptions.iter().map(FhirMedicationRequest::from).collect(),
+        )))
+}
+
+pub(crate) async fn get_prescription_fhir(
+    _user: auth::AuthUser,
+    pool: web::Data<SqlitePool>,
+    prescription_id: web::Path<i64>,
+) -> Result<HttpResponse, Error> {
+    let prescription = sqlx::query_as::<_, Prescription>("SELECT * FROM prescriptions WHERE id = ?")
+        .bind(prescription_id.into_inner())
+        .fetch_optional(pool.get_ref())
+        .await?;
+
+    match prescription {
+        Some(p) => Ok(HttpResponse::Ok()
+            .content_type(FHIR_JSON)
+            .json(FhirMedicationRequest::from(&p))),
+        None => Ok(HttpResponse::NotFound()
+            .content_type(FHIR_JSON)
+            .json(operation_outcome("Prescription not found"))),
+    }
+}
+
+pub(crate) async fn create_prescription_fhir(
+    user: auth::AuthUser,
+    pool: web::Data<SqlitePool>,
+    resource: web::Json<FhirMedicationRequest>,
+) -> Result<HttpResponse, Error> {
+    if let Some(resp) = auth::require_roles(&user, &[auth::Role::Admin, auth::Role::Doctor]) {
+        return Ok(resp);
+    }
+
+    let create = match resource.into_inner().into_create_prescription() {
+        Ok(c) => c,
+        Err(e) => {
+            return Ok(HttpResponse::BadRequest()
+                .content_type(FHIR_JSON)
+                .json(operation_outcome(e)))
+        }
+    };
+
+    let now = Utc::now();
+    let issued = now.to_rfc3339();
+    let expiry = (now + Duration::days((create.duration_days + 90) as i64)).to_rfc3339();
+
+    let mut db = Db::begin(pool.get_ref()).await?;
+
+    let result = sqlx::query(
+        "INSERT INTO prescriptions (patient_id, medication_name, dosage, frequency, duration_days, prescribing_doctor, instructions, issued_date, expiry_date, refills_remaining, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(create.patient_id)
+    .bind(&create.medication_name)
+    .bind(&create.dosage)
+    .bind(&create.frequency)
+    .bind(create.duration_days)
+    .bind(&create.prescribing_doctor)
+    .bind(&create.instructions)
+    .bind(&issued)
+    .bind(&expiry)
+    .bind(create.refills_remaining)
+    .bind(&issued)
+    .execute(db.executor())
+    .await?;
+
+    let created = sqlx::query_as::<_, Prescription>("SELECT * FROM prescriptions WHERE id = ?")
+        .bind(result.last_insert_rowid())
+        .fetch_one(db.executor())
+        .await?;
+
+    db.commit().await?;
+
+    Ok(HttpResponse::Created()
+        .content_type(FHIR_JSON)
+        .json(FhirMedicationRequest::from(&created)))
+}
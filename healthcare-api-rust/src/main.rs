@@ -1,34 +1,43 @@
-use actix_web::{web, App, HttpResponse, HttpServer, Result, middleware};
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, Result, middleware};
 use serde::{Deserialize, Serialize};
-use sqlx::{SqlitePool, FromRow, sqlite::SqliteQueryResult};
-use chrono::{DateTime, Utc, NaiveDateTime};
+use sqlx::{SqlitePool, FromRow, QueryBuilder, Sqlite, sqlite::SqliteQueryResult};
+use chrono::{DateTime, Duration, Utc, NaiveDateTime};
 use std::env;
 
+mod auth;
+mod crypto;
+mod db;
+mod error;
+mod fhir;
+
+use db::Db;
+use error::Error;
+
 #[derive(Debug, Serialize, Deserialize, FromRow)]
-struct Patient {
-    id: i64,
-    first_name: String,
-    last_name: String,
-    email: String,
-    phone: String,
-    date_of_birth: String,
-    address: Option<String>,
-    medical_history: Option<String>,
-    blood_type: Option<String>,
-    created_at: String,
-    updated_at: String,
+pub(crate) struct Patient {
+    pub(crate) id: i64,
+    pub(crate) first_name: String,
+    pub(crate) last_name: String,
+    pub(crate) email: String,
+    pub(crate) phone: String,
+    pub(crate) date_of_birth: String,
+    pub(crate) address: Option<String>,
+    pub(crate) medical_history: Option<String>,
+    pub(crate) blood_type: Option<String>,
+    pub(crate) created_at: String,
+    pub(crate) updated_at: String,
 }
 
 #[derive(Debug, Deserialize)]
-struct CreatePatient {
-    first_name: String,
-    last_name: String,
-    email: String,
-    phone: String,
-    date_of_birth: String,
-    address: Option<String>,
-    medical_history: Option<String>,
-    blood_type: Option<String>,
+pub(crate) struct CreatePatient {
+    pub(crate) first_name: String,
+    pub(crate) last_name: String,
+    pub(crate) email: String,
+    pub(crate) phone: String,
+    pub(crate) date_of_birth: String,
+    pub(crate) address: Option<String>,
+    pub(crate) medical_history: Option<String>,
+    pub(crate) blood_type: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -43,27 +52,27 @@ struct UpdatePatient {
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
-struct Appointment {
-    id: i64,
-    patient_id: i64,
-    doctor_name: String,
-    appointment_date: String,
-    duration_minutes: i32,
-    status: String,
-    reason: String,
-    notes: Option<String>,
-    created_at: String,
-    updated_at: String,
+pub(crate) struct Appointment {
+    pub(crate) id: i64,
+    pub(crate) patient_id: i64,
+    pub(crate) doctor_name: String,
+    pub(crate) appointment_date: String,
+    pub(crate) duration_minutes: i32,
+    pub(crate) status: String,
+    pub(crate) reason: String,
+    pub(crate) notes: Option<String>,
+    pub(crate) created_at: String,
+    pub(crate) updated_at: String,
 }
 
 #[derive(Debug, Deserialize)]
-struct CreateAppointment {
-    patient_id: i64,
-    doctor_name: String,
-    appointment_date: String,
-    duration_minutes: i32,
-    reason: String,
-    notes: Option<String>,
+pub(crate) struct CreateAppointment {
+    pub(crate) patient_id: i64,
+    pub(crate) doctor_name: String,
+    pub(crate) appointment_date: String,
+    pub(crate) duration_minutes: i32,
+    pub(crate) reason: String,
+    pub(crate) notes: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -77,40 +86,79 @@ struct UpdateAppointment {
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
-struct Prescription {
-    id: i64,
-    patient_id: i64,
-    medication_name: String,
-    dosage: String,
-    frequency: String,
-    duration_days: i32,
-    prescribing_doctor: String,
-    instructions: Option<String>,
-    issued_date: String,
-    expiry_date: String,
-    refills_remaining: i32,
-    created_at: String,
+pub(crate) struct Prescription {
+    pub(crate) id: i64,
+    pub(crate) patient_id: i64,
+    pub(crate) medication_name: String,
+    pub(crate) dosage: String,
+    pub(crate) frequency: String,
+    pub(crate) duration_days: i32,
+    pub(crate) prescribing_doctor: String,
+    pub(crate) instructions: Option<String>,
+    pub(crate) issued_date: String,
+    pub(crate) expiry_date: String,
+    pub(crate) refills_remaining: i32,
+    pub(crate) created_at: String,
 }
 
 #[derive(Debug, Deserialize)]
-struct CreatePrescription {
-    patient_id: i64,
-    medication_name: String,
-    dosage: String,
-    frequency: String,
-    duration_days: i32,
-    prescribing_doctor: String,
-    instructions: Option<String>,
-    refills_remaining: i32,
+pub(crate) struct CreatePrescription {
+    pub(crate) patient_id: i64,
+    pub(crate) medication_name: String,
+    pub(crate) dosage: String,
+    pub(crate) frequency: String,
+    pub(crate) duration_days: i32,
+    pub(crate) prescribing_doctor: String,
+    pub(crate) instructions: Option<String>,
+    pub(crate) refills_remaining: i32,
 }
 
 #[derive(Debug, Serialize)]
-struct ApiResponse<T> {
+pub(crate) struct ApiResponse<T> {
     success: bool,
     data: Option<T>,
     message: Option<String>,
 }
 
+// Envelope for list endpoints, extending `ApiResponse` with the pagination
+// metadata a client needs to fetch the next page.
+#[derive(Debug, Serialize)]
+pub(crate) struct PaginatedResponse<T> {
+    success: bool,
+    data: Option<Vec<T>>,
+    message: Option<String>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+}
+
+const DEFAULT_PAGE_LIMIT: i64 = 50;
+const MAX_PAGE_LIMIT: i64 = 200;
+
+#[derive(Debug, Deserialize)]
+struct PatientListParams {
+    limit: Option<i64>,
+    offset: Option<i64>,
+    q: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AppointmentListParams {
+    limit: Option<i64>,
+    offset: Option<i64>,
+    patient_id: Option<i64>,
+    status: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PrescriptionListParams {
+    limit: Option<i64>,
+    offset: Option<i64>,
+    patient_id: Option<i64>,
+}
+
 #[derive(Debug, Serialize)]
 struct HealthCheck {
     status: String,
@@ -120,11 +168,20 @@ struct HealthCheck {
 
 // Patient Handlers
 async fn create_patient(
+    user: auth::AuthUser,
     pool: web::Data<SqlitePool>,
     patient: web::Json<CreatePatient>,
-) -> Result<HttpResponse> {
+) -> Result<HttpResponse, Error> {
+    if let Some(resp) = auth::require_roles(
+        &user,
+        &[auth::Role::Admin, auth::Role::Doctor, auth::Role::Nurse, auth::Role::Receptionist],
+    ) {
+        return Ok(resp);
+    }
+
     let now = Utc::now().to_rfc3339();
-    
+    let mut db = Db::begin(pool.get_ref()).await?;
+
     let result = sqlx::query(
         "INSERT INTO patients (first_name, last_name, email, phone, date_of_birth, address, medical_history, blood_type, created_at, updated_at)
          VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
@@ -139,94 +196,128 @@ async fn create_patient(
     .bind(&patient.blood_type)
     .bind(&now)
     .bind(&now)
-    .execute(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(result) => {
-            let patient_id = result.last_insert_rowid();
-            let created_patient = sqlx::query_as::<_, Patient>(
-                "SELECT * FROM patients WHERE id = ?"
-            )
-            .bind(patient_id)
-            .fetch_one(pool.get_ref())
-            .await
-            .unwrap();
-
-            Ok(HttpResponse::Created().json(ApiResponse {
-                success: true,
-                data: Some(created_patient),
-                message: Some("Patient created successfully".to_string()),
-            }))
+    .execute(db.executor())
+    .await?;
+
+    let patient_id = result.last_insert_rowid();
+    let created_patient = sqlx::query_as::<_, Patient>("SELECT * FROM patients WHERE id = ?")
+        .bind(patient_id)
+        .fetch_one(db.executor())
+        .await?;
+
+    db.commit().await?;
+
+    Ok(HttpResponse::Created().json(ApiResponse {
+        success: true,
+        data: Some(created_patient),
+        message: Some("Patient created successfully".to_string()),
+    }))
+}
+
+async fn get_patients(
+    _user: auth::AuthUser,
+    pool: web::Data<SqlitePool>,
+    params: web::Query<PatientListParams>,
+) -> Result<HttpResponse, Error> {
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT);
+    let offset = params.offset.unwrap_or(0).max(0);
+    let search = params.q.as_ref().filter(|q| !q.is_empty()).map(|q| format!("%{}%", q));
+
+    let mut count_query: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT COUNT(*) FROM patients");
+    let mut list_query: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT * FROM patients");
+
+    if let Some(pattern) = &search {
+        for query in [&mut count_query, &mut list_query] {
+            query
+                .push(" WHERE first_name LIKE ")
+                .push_bind(pattern.clone())
+                .push(" OR last_name LIKE ")
+                .push_bind(pattern.clone())
+                .push(" OR email LIKE ")
+                .push_bind(pattern.clone());
         }
-        Err(e) => Ok(HttpResponse::BadRequest().json(ApiResponse::<Patient> {
-            success: false,
-            data: None,
-            message: Some(format!("Error creating patient: {}", e)),
-        })),
     }
-}
 
-async fn get_patients(pool: web::Data<SqlitePool>) -> Result<HttpResponse> {
-    let patients = sqlx::query_as::<_, Patient>("SELECT * FROM patients ORDER BY created_at DESC")
+    list_query
+        .push(" ORDER BY created_at DESC LIMIT ")
+        .push_bind(limit)
+        .push(" OFFSET ")
+        .push_bind(offset);
+
+    let total: i64 = count_query
+        .build_query_scalar()
+        .fetch_one(pool.get_ref())
+        .await?;
+    let patients = list_query
+        .build_query_as::<Patient>()
         .fetch_all(pool.get_ref())
-        .await
-        .unwrap_or_default();
+        .await?;
 
-    Ok(HttpResponse::Ok().json(ApiResponse {
+    Ok(HttpResponse::Ok().json(PaginatedResponse {
         success: true,
         data: Some(patients),
         message: None,
+        total,
+        limit,
+        offset,
     }))
 }
 
 async fn get_patient(
+    _user: auth::AuthUser,
+    req: HttpRequest,
     pool: web::Data<SqlitePool>,
     patient_id: web::Path<i64>,
-) -> Result<HttpResponse> {
+) -> Result<HttpResponse, Error> {
     let patient = sqlx::query_as::<_, Patient>("SELECT * FROM patients WHERE id = ?")
         .bind(patient_id.into_inner())
         .fetch_optional(pool.get_ref())
-        .await
-        .unwrap();
+        .await?;
 
     match patient {
-        Some(p) => Ok(HttpResponse::Ok().json(ApiResponse {
-            success: true,
-            data: Some(p),
-            message: None,
-        })),
-        None => Ok(HttpResponse::NotFound().json(ApiResponse::<Patient> {
-            success: false,
-            data: None,
-            message: Some("Patient not found".to_string()),
-        })),
+        Some(p) => {
+            if fhir::wants_fhir(&req) {
+                Ok(HttpResponse::Ok()
+                    .content_type(fhir::FHIR_JSON)
+                    .json(fhir::FhirPatient::from(&p)))
+            } else {
+                Ok(HttpResponse::Ok().json(ApiResponse {
+                    success: true,
+                    data: Some(p),
+                    message: None,
+                }))
+            }
+        }
+        None => Err(Error::NotFound("Patient not found".to_string())),
     }
 }
 
 async fn update_patient(
+    user: auth::AuthUser,
     pool: web::Data<SqlitePool>,
     patient_id: web::Path<i64>,
     updates: web::Json<UpdatePatient>,
-) -> Result<HttpResponse> {
+) -> Result<HttpResponse, Error> {
+    if let Some(resp) = auth::require_roles(
+        &user,
+        &[auth::Role::Admin, auth::Role::Doctor, auth::Role::Nurse],
+    ) {
+        return Ok(resp);
+    }
+
     let now = Utc::now().to_rfc3339();
     let id = patient_id.into_inner();
+    let mut db = Db::begin(pool.get_ref()).await?;
 
     let existing = sqlx::query_as::<_, Patient>("SELECT * FROM patients WHERE id = ?")
         .bind(id)
-        .fetch_optional(pool.get_ref())
-        .await
-        .unwrap();
-
-    if existing.is_none() {
-        return Ok(HttpResponse::NotFound().json(ApiResponse::<Patient> {
-            success: false,
-            data: None,
-            message: Some("Patient not found".to_string()),
-        }));
-    }
+        .fetch_optional(db.executor())
+        .await?;
 
-    let patient = existing.unwrap();
+    let patient = match existing {
+        Some(p) => p,
+        None => return Err(Error::NotFound("Patient not found".to_string())),
+    };
 
     sqlx::query(
         "UPDATE patients SET first_name = ?, last_name = ?, email = ?, phone = ?, address = ?, medical_history = ?, blood_type = ?, updated_at = ? WHERE id = ?"
@@ -240,15 +331,15 @@ async fn update_patient(
     .bind(updates.blood_type.as_ref().or(patient.blood_type.as_ref()))
     .bind(&now)
     .bind(id)
-    .execute(pool.get_ref())
-    .await
-    .unwrap();
+    .execute(db.executor())
+    .await?;
 
     let updated_patient = sqlx::query_as::<_, Patient>("SELECT * FROM patients WHERE id = ?")
         .bind(id)
-        .fetch_one(pool.get_ref())
-        .await
-        .unwrap();
+        .fetch_one(db.executor())
+        .await?;
+
+    db.commit().await?;
 
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
@@ -258,32 +349,112 @@ async fn update_patient(
 }
 
 async fn delete_patient(
+    user: auth::AuthUser,
     pool: web::Data<SqlitePool>,
     patient_id: web::Path<i64>,
-) -> Result<HttpResponse> {
+) -> Result<HttpResponse, Error> {
+    if let Some(resp) = auth::require_roles(&user, &[auth::Role::Admin, auth::Role::Doctor]) {
+        return Ok(resp);
+    }
+
+    let mut db = Db::begin(pool.get_ref()).await?;
+
     let result = sqlx::query("DELETE FROM patients WHERE id = ?")
         .bind(patient_id.into_inner())
-        .execute(pool.get_ref())
-        .await
-        .unwrap();
-
-    if result.rows_affected() > 0 {
-        Ok(HttpResponse::NoContent().finish())
-    } else {
-        Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
-            success: false,
-            data: None,
-            message: Some("Patient not found".to_string()),
-        }))
+        .execute(db.executor())
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(Error::NotFound("Patient not found".to_string()));
+    }
+
+    db.commit().await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Checks whether `doctor_name` already has a non-cancelled appointment whose
+// `[appointment_date, appointment_date + duration_minutes)` window overlaps
+// the requested one, returning a 409 `Error::Conflict` naming the offending
+// appointment. `exclude_id` lets `update_appointment` ignore the row it's
+// updating.
+pub(crate) async fn check_appointment_conflict(
+    db: &mut Db,
+    doctor_name: &str,
+    appointment_date: &str,
+    duration_minutes: i32,
+    exclude_id: Option<i64>,
+) -> Result<(), Error> {
+    let start: DateTime<Utc> = appointment_date
+        .parse()
+        .map_err(|_| Error::Validation("appointment_date must be a valid RFC3339 timestamp".to_string()))?;
+
+    if duration_minutes <= 0 {
+        return Err(Error::Validation("duration_minutes must be positive".to_string()));
+    }
+    let end = start + Duration::minutes(duration_minutes as i64);
+
+    let candidates = sqlx::query_as::<_, Appointment>(
+        "SELECT * FROM appointments WHERE doctor_name = ? AND status != 'cancelled' AND id != ?"
+    )
+    .bind(doctor_name)
+    .bind(exclude_id.unwrap_or(0))
+    .fetch_all(db.executor())
+    .await?;
+
+    for candidate in candidates {
+        let other_start: DateTime<Utc> = match candidate.appointment_date.parse() {
+            Ok(d) => d,
+            Err(_) => continue,
+        };
+        let other_end = other_start + Duration::minutes(candidate.duration_minutes as i64);
+
+        if intervals_overlap(start, end, other_start, other_end) {
+            return Err(Error::Conflict(format!(
+                "Conflicts with existing appointment {} for {}",
+                candidate.id, doctor_name
+            )));
+        }
     }
+
+    Ok(())
+}
+
+// Two half-open intervals [a_start, a_end) and [b_start, b_end) overlap iff
+// each starts before the other ends. Back-to-back appointments (one ends
+// exactly when the other starts) do not overlap.
+fn intervals_overlap(a_start: DateTime<Utc>, a_end: DateTime<Utc>, b_start: DateTime<Utc>, b_end: DateTime<Utc>) -> bool {
+    a_start < b_end && b_start < a_end
 }
 
 // Appointment Handlers
 async fn create_appointment(
+    user: auth::AuthUser,
     pool: web::Data<SqlitePool>,
     appointment: web::Json<CreateAppointment>,
-) -> Result<HttpResponse> {
+) -> Result<HttpResponse, Error> {
+    if let Some(resp) = auth::require_roles(
+        &user,
+        &[auth::Role::Admin, auth::Role::Doctor, auth::Role::Nurse, auth::Role::Receptionist],
+    ) {
+        return Ok(resp);
+    }
+
     let now = Utc::now().to_rfc3339();
+    // `check_appointment_conflict` only SELECTs, so a deferred transaction
+    // wouldn't take the write lock until the INSERT below — letting two
+    // overlapping bookings both pass the check before either commits.
+    // BEGIN IMMEDIATE takes the write lock up front and serializes them.
+    let mut db = Db::begin_immediate(pool.get_ref()).await?;
+
+    check_appointment_conflict(
+        &mut db,
+        &appointment.doctor_name,
+        &appointment.appointment_date,
+        appointment.duration_minutes,
+        None,
+    )
+    .await?;
 
     let result = sqlx::query(
         "INSERT INTO appointments (patient_id, doctor_name, appointment_date, duration_minutes, status, reason, notes, created_at, updated_at)
@@ -297,123 +468,198 @@ async fn create_appointment(
     .bind(&appointment.notes)
     .bind(&now)
     .bind(&now)
-    .execute(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(result) => {
-            let appointment_id = result.last_insert_rowid();
-            let created_appointment = sqlx::query_as::<_, Appointment>(
-                "SELECT * FROM appointments WHERE id = ?"
-            )
-            .bind(appointment_id)
-            .fetch_one(pool.get_ref())
-            .await
-            .unwrap();
-
-            Ok(HttpResponse::Created().json(ApiResponse {
-                success: true,
-                data: Some(created_appointment),
-                message: Some("Appointment created successfully".to_string()),
-            }))
+    .execute(db.executor())
+    .await?;
+
+    let appointment_id = result.last_insert_rowid();
+    let created_appointment = sqlx::query_as::<_, Appointment>("SELECT * FROM appointments WHERE id = ?")
+        .bind(appointment_id)
+        .fetch_one(db.executor())
+        .await?;
+
+    db.commit().await?;
+
+    Ok(HttpResponse::Created().json(ApiResponse {
+        success: true,
+        data: Some(created_appointment),
+        message: Some("Appointment created successfully".to_string()),
+    }))
+}
+
+async fn get_appointments(
+    _user: auth::AuthUser,
+    pool: web::Data<SqlitePool>,
+    params: web::Query<AppointmentListParams>,
+) -> Result<HttpResponse, Error> {
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let mut count_query: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT COUNT(*) FROM appointments");
+    let mut list_query: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT * FROM appointments");
+
+    let has_filter = params.patient_id.is_some()
+        || params.status.is_some()
+        || params.from.is_some()
+        || params.to.is_some();
+
+    if has_filter {
+        count_query.push(" WHERE ");
+        list_query.push(" WHERE ");
+        let mut first = true;
+
+        if let Some(patient_id) = params.patient_id {
+            if !first {
+                count_query.push(" AND ");
+                list_query.push(" AND ");
+            }
+            count_query.push("patient_id = ").push_bind(patient_id);
+            list_query.push("patient_id = ").push_bind(patient_id);
+            first = false;
+        }
+        if let Some(status) = &params.status {
+            if !first {
+                count_query.push(" AND ");
+                list_query.push(" AND ");
+            }
+            count_query.push("status = ").push_bind(status.clone());
+            list_query.push("status = ").push_bind(status.clone());
+            first = false;
+        }
+        if let Some(from) = &params.from {
+            if !first {
+                count_query.push(" AND ");
+                list_query.push(" AND ");
+            }
+            count_query.push("appointment_date >= ").push_bind(from.clone());
+            list_query.push("appointment_date >= ").push_bind(from.clone());
+            first = false;
+        }
+        if let Some(to) = &params.to {
+            if !first {
+                count_query.push(" AND ");
+                list_query.push(" AND ");
+            }
+            count_query.push("appointment_date <= ").push_bind(to.clone());
+            list_query.push("appointment_date <= ").push_bind(to.clone());
         }
-        Err(e) => Ok(HttpResponse::BadRequest().json(ApiResponse::<Appointment> {
-            success: false,
-            data: None,
-            message: Some(format!("Error creating appointment: {}", e)),
-        })),
     }
-}
 
-async fn get_appointments(pool: web::Data<SqlitePool>) -> Result<HttpResponse> {
-    let appointments = sqlx::query_as::<_, Appointment>(
-        "SELECT * FROM appointments ORDER BY appointment_date DESC"
-    )
-    .fetch_all(pool.get_ref())
-    .await
-    .unwrap_or_default();
+    list_query
+        .push(" ORDER BY appointment_date DESC LIMIT ")
+        .push_bind(limit)
+        .push(" OFFSET ")
+        .push_bind(offset);
 
-    Ok(HttpResponse::Ok().json(ApiResponse {
+    let total: i64 = count_query
+        .build_query_scalar()
+        .fetch_one(pool.get_ref())
+        .await?;
+    let appointments = list_query
+        .build_query_as::<Appointment>()
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(PaginatedResponse {
         success: true,
         data: Some(appointments),
         message: None,
+        total,
+        limit,
+        offset,
     }))
 }
 
 async fn get_appointment(
+    _user: auth::AuthUser,
+    req: HttpRequest,
     pool: web::Data<SqlitePool>,
     appointment_id: web::Path<i64>,
-) -> Result<HttpResponse> {
+) -> Result<HttpResponse, Error> {
     let appointment = sqlx::query_as::<_, Appointment>(
         "SELECT * FROM appointments WHERE id = ?"
     )
     .bind(appointment_id.into_inner())
     .fetch_optional(pool.get_ref())
-    .await
-    .unwrap();
+    .await?;
 
     match appointment {
-        Some(a) => Ok(HttpResponse::Ok().json(ApiResponse {
-            success: true,
-            data: Some(a),
-            message: None,
-        })),
-        None => Ok(HttpResponse::NotFound().json(ApiResponse::<Appointment> {
-            success: false,
-            data: None,
-            message: Some("Appointment not found".to_string()),
-        })),
+        Some(a) => {
+            if fhir::wants_fhir(&req) {
+                Ok(HttpResponse::Ok()
+                    .content_type(fhir::FHIR_JSON)
+                    .json(fhir::FhirAppointment::from(&a)))
+            } else {
+                Ok(HttpResponse::Ok().json(ApiResponse {
+                    success: true,
+                    data: Some(a),
+                    message: None,
+                }))
+            }
+        }
+        None => Err(Error::NotFound("Appointment not found".to_string())),
     }
 }
 
 async fn update_appointment(
+    user: auth::AuthUser,
     pool: web::Data<SqlitePool>,
     appointment_id: web::Path<i64>,
     updates: web::Json<UpdateAppointment>,
-) -> Result<HttpResponse> {
+) -> Result<HttpResponse, Error> {
+    if let Some(resp) = auth::require_roles(
+        &user,
+        &[auth::Role::Admin, auth::Role::Doctor, auth::Role::Nurse, auth::Role::Receptionist],
+    ) {
+        return Ok(resp);
+    }
+
     let now = Utc::now().to_rfc3339();
     let id = appointment_id.into_inner();
+    // See create_appointment: BEGIN IMMEDIATE so the conflict check below
+    // holds the write lock across the read-then-write, instead of letting a
+    // concurrent update race it to the same doctor/time slot.
+    let mut db = Db::begin_immediate(pool.get_ref()).await?;
 
     let existing = sqlx::query_as::<_, Appointment>(
         "SELECT * FROM appointments WHERE id = ?"
     )
     .bind(id)
-    .fetch_optional(pool.get_ref())
-    .await
-    .unwrap();
-
-    if existing.is_none() {
-        return Ok(HttpResponse::NotFound().json(ApiResponse::<Appointment> {
-            success: false,
-            data: None,
-            message: Some("Appointment not found".to_string()),
-        }));
-    }
+    .fetch_optional(db.executor())
+    .await?;
+
+    let appointment = match existing {
+        Some(a) => a,
+        None => return Err(Error::NotFound("Appointment not found".to_string())),
+    };
 
-    let appointment = existing.unwrap();
+    let doctor_name = updates.doctor_name.as_ref().unwrap_or(&appointment.doctor_name);
+    let appointment_date = updates.appointment_date.as_ref().unwrap_or(&appointment.appointment_date);
+    let duration_minutes = updates.duration_minutes.unwrap_or(appointment.duration_minutes);
+
+    check_appointment_conflict(&mut db, doctor_name, appointment_date, duration_minutes, Some(id)).await?;
 
     sqlx::query(
         "UPDATE appointments SET doctor_name = ?, appointment_date = ?, duration_minutes = ?, status = ?, reason = ?, notes = ?, updated_at = ? WHERE id = ?"
     )
-    .bind(updates.doctor_name.as_ref().unwrap_or(&appointment.doctor_name))
-    .bind(updates.appointment_date.as_ref().unwrap_or(&appointment.appointment_date))
-    .bind(updates.duration_minutes.unwrap_or(appointment.duration_minutes))
+    .bind(doctor_name)
+    .bind(appointment_date)
+    .bind(duration_minutes)
     .bind(updates.status.as_ref().unwrap_or(&appointment.status))
     .bind(updates.reason.as_ref().unwrap_or(&appointment.reason))
     .bind(updates.notes.as_ref().or(appointment.notes.as_ref()))
     .bind(&now)
     .bind(id)
-    .execute(pool.get_ref())
-    .await
-    .unwrap();
+    .execute(db.executor())
+    .await?;
 
     let updated_appointment = sqlx::query_as::<_, Appointment>(
         "SELECT * FROM appointments WHERE id = ?"
     )
     .bind(id)
-    .fetch_one(pool.get_ref())
-    .await
-    .unwrap();
+    .fetch_one(db.executor())
+    .await?;
+
+    db.commit().await?;
 
     Ok(HttpResponse::Ok().json(ApiResponse {
         success: true,
@@ -423,34 +669,44 @@ async fn update_appointment(
 }
 
 async fn delete_appointment(
+    user: auth::AuthUser,
     pool: web::Data<SqlitePool>,
     appointment_id: web::Path<i64>,
-) -> Result<HttpResponse> {
+) -> Result<HttpResponse, Error> {
+    if let Some(resp) = auth::require_roles(&user, &[auth::Role::Admin, auth::Role::Doctor]) {
+        return Ok(resp);
+    }
+
+    let mut db = Db::begin(pool.get_ref()).await?;
+
     let result = sqlx::query("DELETE FROM appointments WHERE id = ?")
         .bind(appointment_id.into_inner())
-        .execute(pool.get_ref())
-        .await
-        .unwrap();
-
-    if result.rows_affected() > 0 {
-        Ok(HttpResponse::NoContent().finish())
-    } else {
-        Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
-            success: false,
-            data: None,
-            message: Some("Appointment not found".to_string()),
-        }))
+        .execute(db.executor())
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(Error::NotFound("Appointment not found".to_string()));
     }
+
+    db.commit().await?;
+
+    Ok(HttpResponse::NoContent().finish())
 }
 
 // Prescription Handlers
 async fn create_prescription(
+    user: auth::AuthUser,
     pool: web::Data<SqlitePool>,
     prescription: web::Json<CreatePrescription>,
-) -> Result<HttpResponse> {
+) -> Result<HttpResponse, Error> {
+    if let Some(resp) = auth::require_roles(&user, &[auth::Role::Admin, auth::Role::Doctor]) {
+        return Ok(resp);
+    }
+
     let now = Utc::now();
     let issued = now.to_rfc3339();
     let expiry = (now + chrono::Duration::days((prescription.duration_days + 90) as i64)).to_rfc3339();
+    let mut db = Db::begin(pool.get_ref()).await?;
 
     let result = sqlx::query(
         "INSERT INTO prescriptions (patient_id, medication_name, dosage, frequency, duration_days, prescribing_doctor, instructions, issued_date, expiry_date, refills_remaining, created_at)
@@ -467,94 +723,119 @@ async fn create_prescription(
     .bind(&expiry)
     .bind(prescription.refills_remaining)
     .bind(&issued)
-    .execute(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(result) => {
-            let prescription_id = result.last_insert_rowid();
-            let created_prescription = sqlx::query_as::<_, Prescription>(
-                "SELECT * FROM prescriptions WHERE id = ?"
-            )
-            .bind(prescription_id)
-            .fetch_one(pool.get_ref())
-            .await
-            .unwrap();
-
-            Ok(HttpResponse::Created().json(ApiResponse {
-                success: true,
-                data: Some(created_prescription),
-                message: Some("Prescription created successfully".to_string()),
-            }))
-        }
-        Err(e) => Ok(HttpResponse::BadRequest().json(ApiResponse::<Prescription> {
-            success: false,
-            data: None,
-            message: Some(format!("Error creating prescription: {}", e)),
-        })),
-    }
+    .execute(db.executor())
+    .await?;
+
+    let prescription_id = result.last_insert_rowid();
+    let created_prescription = sqlx::query_as::<_, Prescription>("SELECT * FROM prescriptions WHERE id = ?")
+        .bind(prescription_id)
+        .fetch_one(db.executor())
+        .await?;
+
+    db.commit().await?;
+
+    Ok(HttpResponse::Created().json(ApiResponse {
+        success: true,
+        data: Some(created_prescription),
+        message: Some("Prescription created successfully".to_string()),
+    }))
 }
 
-async fn get_prescriptions(pool: web::Data<SqlitePool>) -> Result<HttpResponse> {
-    let prescriptions = sqlx::query_as::<_, Prescription>(
-        "SELECT * FROM prescriptions ORDER BY issued_date DESC"
-    )
-    .fetch_all(pool.get_ref())
-    .await
-    .unwrap_or_default();
+async fn get_prescriptions(
+    _user: auth::AuthUser,
+    pool: web::Data<SqlitePool>,
+    params: web::Query<PrescriptionListParams>,
+) -> Result<HttpResponse, Error> {
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT);
+    let offset = params.offset.unwrap_or(0).max(0);
 
-    Ok(HttpResponse::Ok().json(ApiResponse {
+    let mut count_query: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT COUNT(*) FROM prescriptions");
+    let mut list_query: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT * FROM prescriptions");
+
+    if let Some(patient_id) = params.patient_id {
+        count_query.push(" WHERE patient_id = ").push_bind(patient_id);
+        list_query.push(" WHERE patient_id = ").push_bind(patient_id);
+    }
+
+    list_query
+        .push(" ORDER BY issued_date DESC LIMIT ")
+        .push_bind(limit)
+        .push(" OFFSET ")
+        .push_bind(offset);
+
+    let total: i64 = count_query
+        .build_query_scalar()
+        .fetch_one(pool.get_ref())
+        .await?;
+    let prescriptions = list_query
+        .build_query_as::<Prescription>()
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(PaginatedResponse {
         success: true,
         data: Some(prescriptions),
         message: None,
+        total,
+        limit,
+        offset,
     }))
 }
 
 async fn get_prescription(
+    _user: auth::AuthUser,
+    req: HttpRequest,
     pool: web::Data<SqlitePool>,
     prescription_id: web::Path<i64>,
-) -> Result<HttpResponse> {
+) -> Result<HttpResponse, Error> {
     let prescription = sqlx::query_as::<_, Prescription>(
         "SELECT * FROM prescriptions WHERE id = ?"
     )
     .bind(prescription_id.into_inner())
     .fetch_optional(pool.get_ref())
-    .await
-    .unwrap();
+    .await?;
 
     match prescription {
-        Some(p) => Ok(HttpResponse::Ok().json(ApiResponse {
-            success: true,
-            data: Some(p),
-            message: None,
-        })),
-        None => Ok(HttpResponse::NotFound().json(ApiResponse::<Prescription> {
-            success: false,
-            data: None,
-            message: Some("Prescription not found".to_string()),
-        })),
+        Some(p) => {
+            if fhir::wants_fhir(&req) {
+                Ok(HttpResponse::Ok()
+                    .content_type(fhir::FHIR_JSON)
+                    .json(fhir::FhirMedicationRequest::from(&p)))
+            } else {
+                Ok(HttpResponse::Ok().json(ApiResponse {
+                    success: true,
+                    data: Some(p),
+                    message: None,
+                }))
+            }
+        }
+        None => Err(Error::NotFound("Prescription not found".to_string())),
     }
 }
 
 async fn delete_prescription(
+    user: auth::AuthUser,
     pool: web::Data<SqlitePool>,
     prescription_id: web::Path<i64>,
-) -> Result<HttpResponse> {
+) -> Result<HttpResponse, Error> {
+    if let Some(resp) = auth::require_roles(&user, &[auth::Role::Admin, auth::Role::Doctor]) {
+        return Ok(resp);
+    }
+
+    let mut db = Db::begin(pool.get_ref()).await?;
+
     let result = sqlx::query("DELETE FROM prescriptions WHERE id = ?")
         .bind(prescription_id.into_inner())
-        .execute(pool.get_ref())
-        .await
-        .unwrap();
-
-    if result.rows_affected() > 0 {
-        Ok(HttpResponse::NoContent().finish())
-    } else {
-        Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
-            success: false,
-            data: None,
-            message: Some("Prescription not found".to_string()),
-        }))
+        .execute(db.executor())
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(Error::NotFound("Prescription not found".to_string()));
     }
+
+    db.commit().await?;
+
+    Ok(HttpResponse::NoContent().finish())
 }
 
 async fn health_check() -> Result<HttpResponse> {
@@ -565,66 +846,6 @@ async fn health_check() -> Result<HttpResponse> {
     }))
 }
 
-async fn init_db(pool: &SqlitePool) -> Result<(), sqlx::Error> {
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS patients (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            first_name TEXT NOT NULL,
-            last_name TEXT NOT NULL,
-            email TEXT UNIQUE NOT NULL,
-            phone TEXT NOT NULL,
-            date_of_birth TEXT NOT NULL,
-            address TEXT,
-            medical_history TEXT,
-            blood_type TEXT,
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL
-        )"
-    )
-    .execute(pool)
-    .await?;
-
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS appointments (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            patient_id INTEGER NOT NULL,
-            doctor_name TEXT NOT NULL,
-            appointment_date TEXT NOT NULL,
-            duration_minutes INTEGER DEFAULT 30,
-            status TEXT DEFAULT 'scheduled',
-            reason TEXT NOT NULL,
-            notes TEXT,
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL,
-            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
-        )"
-    )
-    .execute(pool)
-    .await?;
-
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS prescriptions (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            patient_id INTEGER NOT NULL,
-            medication_name TEXT NOT NULL,
-            dosage TEXT NOT NULL,
-            frequency TEXT NOT NULL,
-            duration_days INTEGER NOT NULL,
-            prescribing_doctor TEXT NOT NULL,
-            instructions TEXT,
-            issued_date TEXT NOT NULL,
-            expiry_date TEXT NOT NULL,
-            refills_remaining INTEGER DEFAULT 0,
-            created_at TEXT NOT NULL,
-            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
-        )"
-    )
-    .execute(pool)
-    .await?;
-
-    Ok(())
-}
-
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     env::set_var("RUST_LOG", "actix_web=info");
@@ -633,7 +854,10 @@ async fn main() -> std::io::Result<()> {
     let database_url = env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:healthcare.db".to_string());
     let pool = SqlitePool::connect(&database_url).await.expect("Failed to connect to database");
 
-    init_db(&pool).await.expect("Failed to initialize database");
+    sqlx::migrate!("./migrations")
+        .run(&pool)
+        .await
+        .expect("Failed to run database migrations");
 
     println!("ðŸš€ Server starting on http://0.0.0.0:8080");
 
@@ -651,6 +875,13 @@ async fn main() -> std::io::Result<()> {
             .route("/health", web::get().to(health_check))
             .service(
                 web::scope("/api")
+                    .service(
+                        web::scope("/auth")
+                            .route("/register", web::post().to(auth::register))
+                            .route("/login", web::post().to(auth::login))
+                            .route("/forgot-password", web::post().to(auth::forgot_password))
+                            .route("/reset-password", web::post().to(auth::reset_password))
+                    )
                     .service(
                         web::scope("/patients")
                             .route("", web::post().to(create_patient))
@@ -675,8 +906,53 @@ async fn main() -> std::io::Result<()> {
                             .route("/{id}", web::delete().to(delete_prescription))
                     )
             )
+            .service(
+                web::scope("/fhir")
+                    .route("/Patient", web::get().to(fhir::list_patients_fhir))
+                    .route("/Patient/{id}", web::get().to(fhir::get_patient_fhir))
+                    .route("/Patient", web::post().to(fhir::create_patient_fhir))
+                    .route("/Appointment", web::get().to(fhir::list_appointments_fhir))
+                    .route("/Appointment/{id}", web::get().to(fhir::get_appointment_fhir))
+                    .route("/Appointment", web::post().to(fhir::create_appointment_fhir))
+                    .route("/Prescription", web::get().to(fhir::list_prescriptions_fhir))
+                    .route("/Prescription/{id}", web::get().to(fhir::get_prescription_fhir))
+                    .route("/Prescription", web::post().to(fhir::create_prescription_fhir))
+            )
     })
     .bind(("0.0.0.0", 8080))?
     .run()
     .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(minute: i64) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-01-01T09:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+            + Duration::minutes(minute)
+    }
+
+    #[test]
+    fn overlapping_intervals_detected() {
+        assert!(intervals_overlap(at(0), at(30), at(15), at(45)));
+    }
+
+    #[test]
+    fn back_to_back_intervals_do_not_overlap() {
+        assert!(!intervals_overlap(at(0), at(30), at(30), at(60)));
+        assert!(!intervals_overlap(at(30), at(60), at(0), at(30)));
+    }
+
+    #[test]
+    fn disjoint_intervals_do_not_overlap() {
+        assert!(!intervals_overlap(at(0), at(30), at(60), at(90)));
+    }
+
+    #[test]
+    fn containing_interval_overlaps() {
+        assert!(intervals_overlap(at(0), at(60), at(15), at(30)));
+    }
 }
\ No newline at end of file